@@ -10,7 +10,10 @@ use std::{env, net};
 use tokio::io::AsyncWriteExt;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::time::Duration;
+use tokio_rustls::rustls::ServerName;
 use tokio_util::codec::Decoder;
+#[cfg(target_os = "linux")]
+use tokio_vsock::{VsockListener, VsockStream};
 
 #[allow(unused_imports)]
 mod prelude {
@@ -31,10 +34,19 @@ mod handles;
 mod must_initialize;
 mod platform;
 mod python;
+mod recorder;
+mod session_manager;
 mod shared;
 mod stdio_stream;
 mod terminal;
+mod tls;
 
+// NOTE: `matches` is expected to carry `tls-cert`, `tls-key`, `tls-ca-cert`, `tls-client-cert`,
+// `tls-client-key`, `tls-server-name`, `record`, `replay`, `vsock`, `socket`, and `control` -
+// this function reads all of them via `matches.value_of`/`matches.is_present`, but registering
+// them with the `clap::App` that builds `ArgMatches` lives in the CLI entry point, outside this
+// crate's sources. That registration must land alongside this series for any of these flags to
+// be reachable - without it, `clap` will not recognize them on the command line.
 pub fn debug_server(matches: &ArgMatches) -> Result<(), Error> {
     hook_crashes();
 
@@ -75,16 +87,80 @@ pub fn debug_server(matches: &ArgMatches) -> Result<(), Error> {
         }
     };
 
-    let (use_stdio, port, connect) = if let Some(port) = matches.value_of("connect") {
+    let (use_stdio, port, connect) = if matches.is_present("vsock") || matches.is_present("socket") {
+        (false, 0, false)
+    } else if let Some(port) = matches.value_of("connect") {
         (false, port.parse()?, true)
     } else if let Some(port) = matches.value_of("port") {
         (false, port.parse()?, false)
     } else {
         (true, 0, false)
     };
-    let multi_session = matches.is_present("multi-session");
     let auth_token = matches.value_of("auth-token");
 
+    // Server-side TLS is enabled by supplying a certificate and key to wrap the listener in;
+    // client-side TLS is enabled by requesting a TLS server name to verify the peer against.
+    let server_tls_config = match (matches.value_of("tls-cert"), matches.value_of("tls-key")) {
+        (Some(cert_path), Some(key_path)) => Some(tls::ServerTlsConfig {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        }),
+        _ => None,
+    };
+    let tls_server_name = matches.value_of("tls-server-name");
+    let client_tls_config = tls::ClientTlsConfig {
+        ca_cert_path: matches.value_of("tls-ca-cert").map(String::from),
+        client_cert_path: matches.value_of("tls-client-cert").map(String::from),
+        client_key_path: matches.value_of("tls-client-key").map(String::from),
+    };
+    // `client_tls_config` is only ever consulted inside the `tls_server_name` branch below -
+    // if the user supplied CA/pinned-cert or client-cert material but forgot `--tls-server-name`,
+    // that material would be silently ignored and the connection (and any --auth-token) would
+    // go out in cleartext instead of failing loudly. Treat that combination as a configuration
+    // error rather than a silent downgrade.
+    if tls_server_name.is_none()
+        && (client_tls_config.ca_cert_path.is_some()
+            || client_tls_config.client_cert_path.is_some()
+            || client_tls_config.client_key_path.is_some())
+    {
+        return Err(str_error(
+            "--tls-ca-cert/--tls-client-cert/--tls-client-key require --tls-server-name to enable TLS",
+        ));
+    }
+
+    // Record every DAP message exchanged in this process to a transcript, for later
+    // replay via `--replay`, or replay a previously recorded transcript right now.
+    let recorder = match matches.value_of("record") {
+        Some(path) => Some(recorder::Recorder::create(Path::new(path))?),
+        None => None,
+    };
+    let replay_path = matches.value_of("replay").map(Path::new);
+
+    let vsock_addr = matches.value_of("vsock");
+    let socket_path = matches.value_of("socket");
+
+    // When a control endpoint is requested, sessions accepted on any of the data transports
+    // (plain/TLS TCP, vsock, Unix socket) are registered in a `SessionManager` so they can be
+    // listed, killed, or reattached to from that endpoint instead of just running to completion
+    // unobserved - see `dispatch_accepted_session`. The control endpoint itself is always a
+    // loopback TCP listener, regardless of which transport `--vsock`/`--socket` select for data
+    // sessions: `--control <port>` names a *local* port to manage this process from, not a
+    // transport choice, so there is no vsock/Unix-socket equivalent to implement here.
+    let control_addr: Option<net::SocketAddr> = match matches.value_of("control") {
+        Some(port) => Some(net::SocketAddr::new(net::Ipv4Addr::new(127, 0, 0, 1).into(), port.parse()?)),
+        None => None,
+    };
+    let session_manager = control_addr.map(|_| session_manager::SessionManager::new());
+
+    // A session spawned via `spawn_managed_session` is detached with `tokio::spawn` and
+    // expected to outlive the accept loop that created it. Without `--multi-session` the
+    // loop (and then `block_on`) returns after a single accept, and the runtime's 10ms
+    // shutdown grace period would kill that just-spawned background session almost
+    // immediately. The control endpoint's whole point is managing sessions that keep
+    // running after their accepting connection is gone, so `--control` implies
+    // multi-session semantics regardless of whether `--multi-session` was also passed.
+    let multi_session = matches.is_present("multi-session") || session_manager.is_some();
+
     let rt = tokio::runtime::Builder::new_multi_thread() //
         .worker_threads(2)
         .enable_all()
@@ -92,11 +168,47 @@ pub fn debug_server(matches: &ArgMatches) -> Result<(), Error> {
         .unwrap();
 
     rt.block_on(async {
-        if use_stdio {
+        if let (Some(control_addr), Some(manager)) = (control_addr, session_manager.clone()) {
+            let recorder = recorder.clone();
+            let adapter_settings = adapter_settings.clone();
+            let python_interface = python_interface.clone();
+            tokio::spawn(async move {
+                if let Err(err) =
+                    session_manager::run_control_server(control_addr, manager, recorder, adapter_settings, python_interface).await
+                {
+                    error!("Session manager control endpoint failed: {}", err);
+                }
+            });
+        }
+
+        if let Some(replay_path) = replay_path {
+            recorder::replay_session(replay_path, &adapter_settings, &python_interface).await?;
+        } else if use_stdio {
             debug!("Starting on stdio");
             let stream = stdio_stream::StdioStream::new();
             let framed_stream = dap_codec::DAPCodec::new().framed(stream);
-            run_debug_session(Box::new(framed_stream), &adapter_settings, &python_interface).await;
+            run_debug_session(wrap_recorder(Box::new(framed_stream), &recorder), &adapter_settings, &python_interface).await;
+        } else if let Some(vsock_addr) = vsock_addr {
+            run_vsock_transport(
+                vsock_addr,
+                auth_token,
+                multi_session,
+                &recorder,
+                &session_manager,
+                &adapter_settings,
+                &python_interface,
+            )
+            .await?;
+        } else if let Some(socket_path) = socket_path {
+            run_unix_socket_server(
+                socket_path,
+                multi_session,
+                &recorder,
+                &session_manager,
+                &adapter_settings,
+                &python_interface,
+            )
+            .await?;
         } else {
             let localhost = net::Ipv4Addr::new(127, 0, 0, 1);
             let addr = net::SocketAddr::new(localhost.into(), port);
@@ -104,20 +216,63 @@ pub fn debug_server(matches: &ArgMatches) -> Result<(), Error> {
                 debug!("Connecting to {}", addr);
                 let mut tcp_stream = TcpStream::connect(addr).await?;
                 tcp_stream.set_nodelay(true).unwrap();
-                if let Some(auth_token) = auth_token {
-                    let auth_header = format!("Auth-Token: {}\r\n", auth_token);
-                    tcp_stream.write_all(&auth_header.as_bytes()).await?;
+                if let Some(server_name) = tls_server_name {
+                    let connector = tls::build_connector(&client_tls_config)?;
+                    let server_name = ServerName::try_from(server_name)
+                        .map_err(|_| str_error(format!("Invalid TLS server name: {}", server_name)))?;
+                    let mut tls_stream = connector.connect(server_name, tcp_stream).await?;
+                    if let Some(auth_token) = auth_token {
+                        let auth_header = format!("Auth-Token: {}\r\n", auth_token);
+                        tls_stream.write_all(&auth_header.as_bytes()).await?;
+                    }
+                    let framed_stream = dap_codec::DAPCodec::new().framed(tls_stream);
+                    run_debug_session(wrap_recorder(Box::new(framed_stream), &recorder), &adapter_settings, &python_interface).await;
+                } else {
+                    if let Some(auth_token) = auth_token {
+                        let auth_header = format!("Auth-Token: {}\r\n", auth_token);
+                        tcp_stream.write_all(&auth_header.as_bytes()).await?;
+                    }
+                    let framed_stream = dap_codec::DAPCodec::new().framed(tcp_stream);
+                    run_debug_session(wrap_recorder(Box::new(framed_stream), &recorder), &adapter_settings, &python_interface).await;
                 }
-                let framed_stream = dap_codec::DAPCodec::new().framed(tcp_stream);
-                run_debug_session(Box::new(framed_stream), &adapter_settings, &python_interface).await;
+            } else if let Some(server_tls_config) = server_tls_config {
+                let acceptor = tls::build_acceptor(&server_tls_config)?;
+                let listener = TcpListener::bind(&addr).await?;
+                while {
+                    debug!("Listening on {} (TLS)", listener.local_addr()?);
+                    let (tcp_stream, peer_addr) = listener.accept().await?;
+                    tcp_stream.set_nodelay(true).unwrap();
+                    let tls_stream = acceptor.accept(tcp_stream).await?;
+                    let framed_stream = dap_codec::DAPCodec::new().framed(tls_stream);
+                    let channel = wrap_recorder(Box::new(framed_stream), &recorder);
+                    dispatch_accepted_session(
+                        &session_manager,
+                        format!("tcp:{} (tls)", addr),
+                        Some(peer_addr.to_string()),
+                        channel,
+                        &adapter_settings,
+                        &python_interface,
+                    )
+                    .await?;
+                    multi_session
+                } {}
             } else {
                 let listener = TcpListener::bind(&addr).await?;
                 while {
                     debug!("Listening on {}", listener.local_addr()?);
-                    let (tcp_stream, _) = listener.accept().await?;
+                    let (tcp_stream, peer_addr) = listener.accept().await?;
                     tcp_stream.set_nodelay(true).unwrap();
                     let framed_stream = dap_codec::DAPCodec::new().framed(tcp_stream);
-                    run_debug_session(Box::new(framed_stream), &adapter_settings, &python_interface).await;
+                    let channel = wrap_recorder(Box::new(framed_stream), &recorder);
+                    dispatch_accepted_session(
+                        &session_manager,
+                        format!("tcp:{}", addr),
+                        Some(peer_addr.to_string()),
+                        channel,
+                        &adapter_settings,
+                        &python_interface,
+                    )
+                    .await?;
                     multi_session
                 } {}
             }
@@ -128,6 +283,10 @@ pub fn debug_server(matches: &ArgMatches) -> Result<(), Error> {
 
     rt.shutdown_timeout(Duration::from_millis(10));
 
+    if let Some(socket_path) = socket_path {
+        let _ = std::fs::remove_file(socket_path);
+    }
+
     finalize_reproducer();
     debug!("Exiting");
     #[cfg(not(windows))]
@@ -135,7 +294,180 @@ pub fn debug_server(matches: &ArgMatches) -> Result<(), Error> {
     Ok(())
 }
 
-async fn run_debug_session(
+#[cfg(unix)]
+async fn run_unix_socket_server(
+    socket_path: &str,
+    multi_session: bool,
+    recorder: &Option<Arc<recorder::Recorder>>,
+    session_manager: &Option<session_manager::SessionManager>,
+    adapter_settings: &adapter_protocol::AdapterSettings,
+    python_interface: &Option<Arc<python::PythonInterface>>,
+) -> Result<(), Error> {
+    let listener = tokio::net::UnixListener::bind(socket_path)?;
+    while {
+        debug!("Listening on {}", socket_path);
+        let (unix_stream, _) = listener.accept().await?;
+        let framed_stream = dap_codec::DAPCodec::new().framed(unix_stream);
+        let channel = wrap_recorder(Box::new(framed_stream), recorder);
+        dispatch_accepted_session(
+            session_manager,
+            format!("unix:{}", socket_path),
+            None,
+            channel,
+            adapter_settings,
+            python_interface,
+        )
+        .await?;
+        multi_session
+    } {}
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn run_unix_socket_server(
+    _socket_path: &str,
+    _multi_session: bool,
+    _recorder: &Option<Arc<recorder::Recorder>>,
+    _session_manager: &Option<session_manager::SessionManager>,
+    _adapter_settings: &adapter_protocol::AdapterSettings,
+    _python_interface: &Option<Arc<python::PythonInterface>>,
+) -> Result<(), Error> {
+    Err(str_error("Unix domain sockets (--socket) are not supported on this platform"))
+}
+
+// Parses a `cid:port` vsock address, accepting "any" (mapped to `VMADDR_CID_ANY`) as the
+// CID for the listening side. AF_VSOCK (and `libc::VMADDR_CID_ANY`) is Linux/Android-only,
+// so both this and its caller are gated to `target_os = "linux"`.
+#[cfg(target_os = "linux")]
+fn parse_vsock_addr(s: &str) -> Result<(u32, u32), Error> {
+    let (cid_str, port_str) =
+        s.split_once(':').ok_or_else(|| str_error(format!("Invalid vsock address: {} (expected cid:port)", s)))?;
+    let cid = if cid_str.eq_ignore_ascii_case("any") {
+        libc::VMADDR_CID_ANY
+    } else {
+        cid_str.parse().map_err(|_| str_error(format!("Invalid vsock CID: {}", cid_str)))?
+    };
+    let port = port_str.parse().map_err(|_| str_error(format!("Invalid vsock port: {}", port_str)))?;
+    Ok((cid, port))
+}
+
+#[cfg(target_os = "linux")]
+async fn run_vsock_transport(
+    vsock_addr: &str,
+    auth_token: Option<&str>,
+    multi_session: bool,
+    recorder: &Option<Arc<recorder::Recorder>>,
+    session_manager: &Option<session_manager::SessionManager>,
+    adapter_settings: &adapter_protocol::AdapterSettings,
+    python_interface: &Option<Arc<python::PythonInterface>>,
+) -> Result<(), Error> {
+    let (cid, vport) = parse_vsock_addr(vsock_addr)?;
+    if cid == libc::VMADDR_CID_ANY {
+        let mut listener = VsockListener::bind(cid, vport)?;
+        while {
+            debug!("Listening on vsock {}:{}", cid, vport);
+            let (vsock_stream, peer_cid) = listener.accept().await?;
+            let framed_stream = dap_codec::DAPCodec::new().framed(vsock_stream);
+            let channel = wrap_recorder(Box::new(framed_stream), recorder);
+            dispatch_accepted_session(
+                session_manager,
+                format!("vsock:{}:{}", cid, vport),
+                Some(format!("{:?}", peer_cid)),
+                channel,
+                adapter_settings,
+                python_interface,
+            )
+            .await?;
+            multi_session
+        } {}
+    } else {
+        debug!("Connecting to vsock {}:{}", cid, vport);
+        let mut vsock_stream = VsockStream::connect(cid, vport).await?;
+        if let Some(auth_token) = auth_token {
+            let auth_header = format!("Auth-Token: {}\r\n", auth_token);
+            vsock_stream.write_all(&auth_header.as_bytes()).await?;
+        }
+        let framed_stream = dap_codec::DAPCodec::new().framed(vsock_stream);
+        run_debug_session(wrap_recorder(Box::new(framed_stream), recorder), adapter_settings, python_interface).await;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn run_vsock_transport(
+    _vsock_addr: &str,
+    _auth_token: Option<&str>,
+    _multi_session: bool,
+    _recorder: &Option<Arc<recorder::Recorder>>,
+    _session_manager: &Option<session_manager::SessionManager>,
+    _adapter_settings: &adapter_protocol::AdapterSettings,
+    _python_interface: &Option<Arc<python::PythonInterface>>,
+) -> Result<(), Error> {
+    Err(str_error("AF_VSOCK (--vsock) is only supported on Linux"))
+}
+
+// Hands a freshly accepted `channel` off to the session manager if one is configured,
+// otherwise just runs it to completion inline - the single piece of dispatch logic
+// every listener branch (TCP, TLS, vsock, Unix socket) shares, so that `--control`
+// manages sessions regardless of which transport they came in on.
+async fn dispatch_accepted_session(
+    session_manager: &Option<session_manager::SessionManager>,
+    target: String,
+    client_addr: Option<String>,
+    channel: Box<dyn DAPChannel>,
+    adapter_settings: &adapter_protocol::AdapterSettings,
+    python_interface: &Option<Arc<python::PythonInterface>>,
+) -> Result<(), Error> {
+    match session_manager {
+        Some(manager) => spawn_managed_session(
+            manager.clone(),
+            target,
+            client_addr,
+            channel,
+            adapter_settings.clone(),
+            python_interface.clone(),
+        ),
+        None => {
+            run_debug_session(channel, adapter_settings, python_interface).await;
+            Ok(())
+        }
+    }
+}
+
+// Registers a just-accepted `channel` with the session manager, immediately attaches it
+// (it's already a live connection, not a later reconnect), then runs the session in the
+// background so the accept loop isn't blocked for the session's lifetime.
+fn spawn_managed_session(
+    manager: session_manager::SessionManager,
+    target: String,
+    client_addr: Option<String>,
+    channel: Box<dyn DAPChannel>,
+    adapter_settings: adapter_protocol::AdapterSettings,
+    python_interface: Option<Arc<python::PythonInterface>>,
+) -> Result<(), Error> {
+    let (id, relay_channel, mut kill_rx) = manager.register(target, client_addr);
+    manager.attach(id, channel)?;
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = run_debug_session(relay_channel, &adapter_settings, &python_interface) => {}
+            _ = kill_rx.recv() => debug!("Session {} killed", id),
+        }
+        manager.deregister(id);
+    });
+    Ok(())
+}
+
+pub(crate) fn wrap_recorder(channel: Box<dyn DAPChannel>, recorder: &Option<Arc<recorder::Recorder>>) -> Box<dyn DAPChannel> {
+    match recorder {
+        Some(recorder) => {
+            let session_id = recorder.new_session_id();
+            Box::new(recorder::RecordingChannel::new(channel, recorder.clone(), session_id))
+        }
+        None => channel,
+    }
+}
+
+pub(crate) async fn run_debug_session(
     framed_stream: Box<dyn DAPChannel>,
     adapter_settings: &adapter_protocol::AdapterSettings,
     python_interface: &Option<Arc<python::PythonInterface>>,
@@ -202,3 +534,30 @@ fn finalize_reproducer() {
         }
     }
 }
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::parse_vsock_addr;
+
+    #[test]
+    fn parses_explicit_cid_and_port() {
+        assert_eq!(parse_vsock_addr("3:9000").unwrap(), (3, 9000));
+    }
+
+    #[test]
+    fn parses_any_cid_case_insensitively_for_listening() {
+        assert_eq!(parse_vsock_addr("any:9000").unwrap(), (libc::VMADDR_CID_ANY, 9000));
+        assert_eq!(parse_vsock_addr("ANY:9000").unwrap(), (libc::VMADDR_CID_ANY, 9000));
+    }
+
+    #[test]
+    fn rejects_missing_separator() {
+        assert!(parse_vsock_addr("9000").is_err());
+    }
+
+    #[test]
+    fn rejects_non_numeric_port() {
+        assert!(parse_vsock_addr("3:not-a-port").is_err());
+    }
+}
@@ -0,0 +1,378 @@
+use crate::dap_session::DAPChannel;
+use crate::prelude::*;
+use adapter_protocol::ProtocolMessage;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+pub type SessionId = u64;
+
+// One row of the session table: metadata tracked about a live `DebugSession`, plus the
+// means to splice a freshly accepted `DAPChannel` into it on `attach`.
+//
+// There's no `pid` column here: the inferior's pid is only known inside `DebugSession`
+// itself (once it launches/attaches), and nothing in this crate plumbs it back out to
+// `run_debug_session`'s caller. Tracking it would mean either reaching into
+// `debug_session` internals that aren't exposed for this, or shipping a column that's
+// always null - so it's left out until there's a real way to populate it.
+struct SessionEntry {
+    target: String,
+    started: SystemTime,
+    client_addr: Option<String>,
+    attach_tx: mpsc::UnboundedSender<Box<dyn DAPChannel>>,
+    kill_tx: mpsc::UnboundedSender<()>,
+}
+
+#[derive(Serialize)]
+pub struct SessionSummary {
+    id: SessionId,
+    target: String,
+    started: u64,
+    client_addr: Option<String>,
+}
+
+// Shared registry of every `DebugSession` this process is currently managing, plus
+// the attach/list/spawn/kill control surface described on top of it.
+#[derive(Clone)]
+pub struct SessionManager {
+    sessions: Arc<Mutex<HashMap<SessionId, SessionEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl SessionManager {
+    pub fn new() -> SessionManager {
+        SessionManager {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    // Registers a new session, returning its id, a `DAPChannel` to hand to `run_debug_session`,
+    // and a future that resolves once the session should be torn down (on `kill` or on the
+    // session's own `attach_tx` being dropped).
+    pub fn register(&self, target: String, client_addr: Option<String>) -> (SessionId, Box<dyn DAPChannel>, mpsc::UnboundedReceiver<()>) {
+        let (relay_end, attach_tx) = spawn_session_relay();
+        let (kill_tx, kill_rx) = mpsc::unbounded_channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let entry = SessionEntry {
+            target,
+            started: SystemTime::now(),
+            client_addr,
+            attach_tx,
+            kill_tx,
+        };
+        self.sessions.lock().unwrap().insert(id, entry);
+        (id, Box::new(relay_end), kill_rx)
+    }
+
+    pub fn deregister(&self, id: SessionId) {
+        self.sessions.lock().unwrap().remove(&id);
+    }
+
+    pub fn contains(&self, id: SessionId) -> bool {
+        self.sessions.lock().unwrap().contains_key(&id)
+    }
+
+    fn list(&self) -> Vec<SessionSummary> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&id, entry)| SessionSummary {
+                id,
+                target: entry.target.clone(),
+                started: entry.started.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+                client_addr: entry.client_addr.clone(),
+            })
+            .collect()
+    }
+
+    pub fn attach(&self, id: SessionId, channel: Box<dyn DAPChannel>) -> Result<(), Error> {
+        let sessions = self.sessions.lock().unwrap();
+        match sessions.get(&id) {
+            Some(entry) => entry
+                .attach_tx
+                .send(channel)
+                .map_err(|_| str_error(format!("Session {} is no longer accepting attachments", id))),
+            None => Err(str_error(format!("No such session: {}", id))),
+        }
+    }
+
+    fn kill(&self, id: SessionId) -> Result<(), Error> {
+        let sessions = self.sessions.lock().unwrap();
+        match sessions.get(&id) {
+            Some(entry) => entry.kill_tx.send(()).map_err(|_| str_error(format!("Session {} already exited", id))),
+            None => Err(str_error(format!("No such session: {}", id))),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum ControlRequest {
+    List,
+    Spawn { target: String },
+    Attach { id: SessionId },
+    Kill { id: SessionId },
+}
+
+// Wire shapes, one per line of control-endpoint response: `Sessions` and `Spawned` are
+// self-describing objects/arrays, `Error` is `{"error": "..."}`, and `Ok` - being a unit
+// variant under `#[serde(untagged)]` - serializes to a bare JSON `null`. That's
+// distinguishable from the other three shapes (none of which can themselves be `null`), so
+// clients should treat a response line of exactly `null` as success for `kill`/`attach`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ControlResponse {
+    Sessions(Vec<SessionSummary>),
+    Spawned { id: SessionId },
+    Ok,
+    Error { error: String },
+}
+
+// Accepts connections on a control endpoint (always loopback TCP, independent of whichever
+// transport - TCP, TLS, vsock, or Unix socket - the data sessions themselves use) and
+// dispatches each one's `list`/`spawn`/`attach`/`kill` request. `spawn` and `attach`
+// register/splice a new debug session using the same plumbing as the transport loops in
+// `lib.rs`.
+pub async fn run_control_server(
+    addr: std::net::SocketAddr,
+    manager: SessionManager,
+    recorder: Option<Arc<crate::recorder::Recorder>>,
+    adapter_settings: adapter_protocol::AdapterSettings,
+    python_interface: Option<Arc<crate::python::PythonInterface>>,
+) -> Result<(), Error> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Session manager control endpoint listening on {}", addr);
+    loop {
+        let (stream, peer_addr) = listener.accept().await?;
+        let manager = manager.clone();
+        let recorder = recorder.clone();
+        let adapter_settings = adapter_settings.clone();
+        let python_interface = python_interface.clone();
+        tokio::spawn(async move {
+            if let Err(err) =
+                handle_control_connection(stream, peer_addr, manager, recorder, adapter_settings, python_interface).await
+            {
+                warn!("Control connection from {} failed: {}", peer_addr, err);
+            }
+        });
+    }
+}
+
+async fn handle_control_connection(
+    stream: TcpStream,
+    peer_addr: std::net::SocketAddr,
+    manager: SessionManager,
+    recorder: Option<Arc<crate::recorder::Recorder>>,
+    adapter_settings: adapter_protocol::AdapterSettings,
+    python_interface: Option<Arc<crate::python::PythonInterface>>,
+) -> Result<(), Error> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let request: ControlRequest =
+        serde_json::from_str(line.trim()).map_err(|err| str_error(format!("Malformed control request: {}", err)))?;
+
+    match request {
+        ControlRequest::List => {
+            let response = ControlResponse::Sessions(manager.list());
+            write_response(&mut reader, &response).await
+        }
+        ControlRequest::Kill { id } => {
+            let response = match manager.kill(id) {
+                Ok(()) => ControlResponse::Ok,
+                Err(err) => ControlResponse::Error { error: err.to_string() },
+            };
+            write_response(&mut reader, &response).await
+        }
+        ControlRequest::Spawn { target } => {
+            let (id, channel, mut kill_rx) = manager.register(target, Some(peer_addr.to_string()));
+            write_response(&mut reader, &ControlResponse::Spawned { id }).await?;
+            let channel = crate::wrap_recorder(channel, &recorder);
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = crate::run_debug_session(channel, &adapter_settings, &python_interface) => {}
+                    _ = kill_rx.recv() => debug!("Session {} killed", id),
+                }
+                manager.deregister(id);
+            });
+            Ok(())
+        }
+        ControlRequest::Attach { id } => {
+            // Validate the session exists before acking `Ok` and taking over the stream -
+            // otherwise a client that gets told `Ok` for an id that's already gone ends up
+            // spliced into nothing, with the failure only ever logged server-side.
+            if !manager.contains(id) {
+                let response = ControlResponse::Error { error: format!("No such session: {}", id) };
+                return write_response(&mut reader, &response).await;
+            }
+            write_response(&mut reader, &ControlResponse::Ok).await?;
+            // `reader.into_inner()` only returns the underlying stream - any bytes a client
+            // pipelined right after the attach request line (instead of waiting for this `Ok`
+            // ack before sending DAP traffic) are sitting in the BufReader's internal buffer
+            // and would otherwise be silently dropped. Carry them over into the codec's buffer
+            // before handing the framed stream off.
+            let buffered = reader.buffer().to_vec();
+            let tcp_stream = reader.into_inner();
+            let mut framed_stream = crate::dap_codec::DAPCodec::new().framed(tcp_stream);
+            if !buffered.is_empty() {
+                framed_stream.read_buffer_mut().extend_from_slice(&buffered);
+            }
+            manager.attach(id, Box::new(framed_stream))
+        }
+    }
+}
+
+async fn write_response(reader: &mut BufReader<TcpStream>, response: &ControlResponse) -> Result<(), Error> {
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    reader.get_mut().write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+// Forwards DAP messages between whichever transport is currently attached to a session and
+// the `DAPSession` running it, so that the session survives its client disconnecting and
+// another one attaching later (via `ControlRequest::Attach`).
+struct SessionRelayEnd {
+    inbound: mpsc::UnboundedReceiver<Result<ProtocolMessage, Error>>,
+    outbound: mpsc::UnboundedSender<ProtocolMessage>,
+}
+
+impl Stream for SessionRelayEnd {
+    type Item = Result<ProtocolMessage, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.inbound.poll_recv(cx)
+    }
+}
+
+impl Sink<ProtocolMessage> for SessionRelayEnd {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: ProtocolMessage) -> Result<(), Error> {
+        self.outbound.send(item).map_err(|_| str_error("Session relay is no longer running"))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl DAPChannel for SessionRelayEnd {}
+
+fn spawn_session_relay() -> (SessionRelayEnd, mpsc::UnboundedSender<Box<dyn DAPChannel>>) {
+    let (inbound_tx, inbound_rx) = mpsc::unbounded_channel::<Result<ProtocolMessage, Error>>();
+    let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<ProtocolMessage>();
+    let (attach_tx, mut attach_rx) = mpsc::unbounded_channel::<Box<dyn DAPChannel>>();
+
+    tokio::spawn(async move {
+        let mut active: Option<Box<dyn DAPChannel>> = None;
+        loop {
+            tokio::select! {
+                new_channel = attach_rx.recv() => match new_channel {
+                    Some(new_channel) => {
+                        debug!("Session relay: new client attached");
+                        active = Some(new_channel);
+                    }
+                    None => break,
+                },
+                Some(message) = outbound_rx.recv() => {
+                    if let Some(channel) = active.as_mut() {
+                        if let Err(err) = channel.send(message).await {
+                            warn!("Session relay: client disconnected: {}", err);
+                            active = None;
+                        }
+                    }
+                }
+                Some(item) = async { active.as_mut()?.next().await }, if active.is_some() => {
+                    if inbound_tx.send(item).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    (SessionRelayEnd { inbound: inbound_rx, outbound: outbound_tx }, attach_tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn control_request_list_deserializes() {
+        let request: ControlRequest = serde_json::from_str(r#"{"command":"list"}"#).unwrap();
+        assert!(matches!(request, ControlRequest::List));
+    }
+
+    #[test]
+    fn control_request_spawn_deserializes_target() {
+        let request: ControlRequest = serde_json::from_str(r#"{"command":"spawn","target":"/bin/true"}"#).unwrap();
+        match request {
+            ControlRequest::Spawn { target } => assert_eq!(target, "/bin/true"),
+            _ => panic!("expected Spawn"),
+        }
+    }
+
+    #[test]
+    fn control_request_attach_and_kill_deserialize_id() {
+        let request: ControlRequest = serde_json::from_str(r#"{"command":"attach","id":7}"#).unwrap();
+        assert!(matches!(request, ControlRequest::Attach { id: 7 }));
+
+        let request: ControlRequest = serde_json::from_str(r#"{"command":"kill","id":7}"#).unwrap();
+        assert!(matches!(request, ControlRequest::Kill { id: 7 }));
+    }
+
+    #[test]
+    fn control_request_rejects_unknown_command() {
+        let result: Result<ControlRequest, _> = serde_json::from_str(r#"{"command":"bogus"}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn control_response_sessions_serializes_as_array() {
+        let response = ControlResponse::Sessions(vec![SessionSummary {
+            id: 1,
+            target: "tcp:127.0.0.1:9000".into(),
+            started: 0,
+            client_addr: Some("127.0.0.1:54321".into()),
+        }]);
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            value,
+            json!([{"id": 1, "target": "tcp:127.0.0.1:9000", "started": 0, "client_addr": "127.0.0.1:54321"}])
+        );
+    }
+
+    #[test]
+    fn control_response_error_serializes_with_message() {
+        let response = ControlResponse::Error { error: "No such session: 7".into() };
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value, json!({"error": "No such session: 7"}));
+    }
+
+    #[test]
+    fn control_response_ok_serializes_as_bare_null() {
+        let value = serde_json::to_value(&ControlResponse::Ok).unwrap();
+        assert_eq!(value, json!(null));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::prelude::*;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::{self, Certificate, OwnedTrustAnchor, PrivateKey};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+// Server-side identity used to accept TLS connections on the listening socket.
+pub struct ServerTlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+// Client-side trust material used when dialing a TLS-protected adapter.
+pub struct ClientTlsConfig {
+    pub ca_cert_path: Option<String>,
+    pub client_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Certificate>, Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let certs = rustls_pemfile::certs(&mut reader)
+        .map_err(|_| str_error(format!("Could not parse certificate(s) in {:?}", path)))?;
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+// Accepts PKCS#8 (`BEGIN PRIVATE KEY`), PKCS#1 (`BEGIN RSA PRIVATE KEY`), and SEC1
+// (`BEGIN EC PRIVATE KEY`) PEM files, since the request places no format restriction
+// on the key a user points `--tls-key`/`--tls-client-key` at.
+fn load_private_key(path: &Path) -> Result<PrivateKey, Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    loop {
+        match rustls_pemfile::read_one(&mut reader)
+            .map_err(|_| str_error(format!("Could not parse private key in {:?}", path)))?
+        {
+            Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => return Err(str_error(format!("No private key found in {:?}", path))),
+        }
+    }
+}
+
+// Builds an acceptor for the server side of a `--cert`/`--key` TCP listener.
+pub fn build_acceptor(config: &ServerTlsConfig) -> Result<TlsAcceptor, Error> {
+    let certs = load_certs(Path::new(&config.cert_path))?;
+    let key = load_private_key(Path::new(&config.key_path))?;
+    let server_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| str_error(format!("Invalid server certificate/key: {}", err)))?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+// Builds a connector for the client side of `--connect`, trusting either a CA/pinned
+// certificate supplied via `ca_cert_path`, or the platform's built-in web roots, and
+// optionally presenting a client certificate for mutual TLS.
+pub fn build_connector(config: &ClientTlsConfig) -> Result<TlsConnector, Error> {
+    let mut root_store = rustls::RootCertStore::empty();
+    match &config.ca_cert_path {
+        Some(path) => {
+            for cert in load_certs(Path::new(path))? {
+                root_store
+                    .add(&cert)
+                    .map_err(|err| str_error(format!("Invalid CA/pinned certificate: {}", err)))?;
+            }
+        }
+        None => root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+        })),
+    }
+
+    let builder = rustls::ClientConfig::builder().with_safe_defaults().with_root_certificates(root_store);
+
+    let client_config = match (&config.client_cert_path, &config.client_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let certs = load_certs(Path::new(cert_path))?;
+            let key = load_private_key(Path::new(key_path))?;
+            builder
+                .with_client_auth_cert(certs, key)
+                .map_err(|err| str_error(format!("Invalid client certificate/key: {}", err)))?
+        }
+        _ => builder.with_no_client_auth(),
+    };
+
+    Ok(TlsConnector::from(Arc::new(client_config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const TEST_CERT: &str = include_str!("../test_data/test_cert.pem");
+    const TEST_KEY_PKCS8: &str = include_str!("../test_data/test_key_pkcs8.pem");
+    const TEST_KEY_PKCS1: &str = include_str!("../test_data/test_key_pkcs1.pem");
+    const TEST_KEY_EC: &str = include_str!("../test_data/test_key_ec.pem");
+
+    fn write_temp(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    #[test]
+    fn loads_a_single_certificate() {
+        let file = write_temp(TEST_CERT);
+        let certs = load_certs(file.path()).unwrap();
+        assert_eq!(certs.len(), 1);
+    }
+
+    #[test]
+    fn loads_pkcs8_private_key() {
+        let file = write_temp(TEST_KEY_PKCS8);
+        load_private_key(file.path()).unwrap();
+    }
+
+    #[test]
+    fn loads_pkcs1_rsa_private_key() {
+        let file = write_temp(TEST_KEY_PKCS1);
+        load_private_key(file.path()).unwrap();
+    }
+
+    #[test]
+    fn loads_sec1_ec_private_key() {
+        let file = write_temp(TEST_KEY_EC);
+        load_private_key(file.path()).unwrap();
+    }
+
+    #[test]
+    fn build_acceptor_accepts_matching_cert_and_key() {
+        let cert_file = write_temp(TEST_CERT);
+        let key_file = write_temp(TEST_KEY_PKCS8);
+        build_acceptor(&ServerTlsConfig {
+            cert_path: cert_file.path().to_str().unwrap().to_owned(),
+            key_path: key_file.path().to_str().unwrap().to_owned(),
+        })
+        .unwrap();
+    }
+}